@@ -1,4 +1,5 @@
 use chrono::{DateTime, FixedOffset, Local, Offset, Utc};
+use chrono_tz::Tz;
 use env_logger::fmt::{style, Formatter};
 use env_logger::{TimestampPrecision};
 use log::Record;
@@ -10,16 +11,85 @@ use std::time::SystemTime;
 const DATETIME_FMT_SECOND: &str = "%Y-%m-%d %H:%M:%S %:z";
 const DATETIME_FMT_3F: &str = "%Y-%m-%d %H:%M:%S%.3f %:z";
 const DATETIME_FMT_6F: &str = "%Y-%m-%d %H:%M:%S%.6f %:z";
+const DATETIME_FMT_9F: &str = "%Y-%m-%d %H:%M:%S%.9f %:z";
+
+/// Selects how each record is rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The bracketed, human-readable header followed by the message.
+    Human,
+    /// One JSON object per record, suitable for NDJSON log pipelines. The
+    /// `timestamp` field is always an RFC3339 string in the configured zone;
+    /// the human-only `datetime_fmt` pattern does not apply to JSON output.
+    Json,
+}
+
+/// The zone used to render timestamps: either a fixed UTC offset or a named
+/// IANA zone that follows its own daylight-saving rules.
+#[derive(Clone, Copy, Debug)]
+pub enum TimeZone {
+    /// A constant offset from UTC, e.g. `+08:00`.
+    Fixed(FixedOffset),
+    /// A named IANA zone (e.g. `America/New_York`) resolved through `chrono-tz`.
+    Named(Tz),
+}
+
+impl TimeZone {
+    /// Formats the current instant in this zone using a chrono strftime pattern.
+    fn format_now(&self, fmt: &str) -> String {
+        let utc = DateTime::<Utc>::from(SystemTime::now());
+        match self {
+            TimeZone::Fixed(offset) => utc.with_timezone(offset).format(fmt).to_string(),
+            TimeZone::Named(tz) => utc.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+
+    /// Formats the current instant in this zone as an RFC3339 string.
+    fn now_rfc3339(&self) -> String {
+        let utc = DateTime::<Utc>::from(SystemTime::now());
+        match self {
+            TimeZone::Fixed(offset) => utc.with_timezone(offset).to_rfc3339(),
+            TimeZone::Named(tz) => utc.with_timezone(tz).to_rfc3339(),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct TimeZoneFormatEnv {
-    pub datetime_fmt: &'static str,
-    pub offset: FixedOffset,
+    pub datetime_fmt: String,
+    pub offset: TimeZone,
     pub module_path: bool,
     pub target: bool,
     pub level: bool,
     pub indent: Option<usize>,
     pub suffix: &'static str,
+    pub kv: bool,
+    pub kv_separator: &'static str,
+    pub output_format: OutputFormat,
+    pub styles: TimeZoneStyles,
+}
+
+/// ANSI styles applied to each component of the human-readable header. The
+/// level keeps using the formatter's `default_level_style`; everything else is
+/// configurable here. Colors are emitted unconditionally and stripped by the
+/// formatter's underlying stream when the target is not a TTY.
+#[derive(Clone, Debug)]
+pub struct TimeZoneStyles {
+    pub brackets: style::Style,
+    pub timestamp: style::Style,
+    pub target: style::Style,
+    pub module_path: style::Style,
+}
+
+impl Default for TimeZoneStyles {
+    fn default() -> Self {
+        Self {
+            brackets: style::Style::new().dimmed(),
+            timestamp: style::Style::new(),
+            target: style::Style::new(),
+            module_path: style::Style::new(),
+        }
+    }
 }
 
 impl Default for TimeZoneFormatEnv {
@@ -30,34 +100,87 @@ impl Default for TimeZoneFormatEnv {
 
 impl TimeZoneFormatEnv {
     pub fn new(offset_value: Option<i32>, timestamp_precision: Option<TimestampPrecision>) -> Self {
-        let offset = if let Some(offset_value) = offset_value {
+        let fixed = if let Some(offset_value) = offset_value {
             FixedOffset::east_opt(offset_value).unwrap_or(Local::now().offset().fix())
         } else {
             Local::now().offset().fix()
         };
+        Self::with_zone(TimeZone::Fixed(fixed), timestamp_precision)
+    }
+
+    /// Builds an env whose timestamps follow a named IANA zone (with DST),
+    /// e.g. `"America/New_York"` or `"Asia/Shanghai"`.
+    ///
+    /// Returns the `chrono-tz` parse error message if the identifier is unknown.
+    pub fn from_tz_name(
+        tz_name: &str,
+        timestamp_precision: Option<TimestampPrecision>,
+    ) -> Result<Self, String> {
+        let tz: Tz = tz_name
+            .parse()
+            .map_err(|e: chrono_tz::ParseError| e.to_string())?;
+        Ok(Self::with_zone(TimeZone::Named(tz), timestamp_precision))
+    }
+
+    /// Overrides the timestamp format with an arbitrary chrono strftime
+    /// pattern, e.g. `"%Y-%m-%dT%H:%M:%S%.9f%:z"`.
+    ///
+    /// The pattern is validated immediately so a typo surfaces here instead of
+    /// panicking the first time a record is logged.
+    pub fn with_datetime_fmt(mut self, datetime_fmt: impl Into<String>) -> Result<Self, String> {
+        let datetime_fmt = datetime_fmt.into();
+        Self::validate_datetime_fmt(&datetime_fmt)?;
+        self.datetime_fmt = datetime_fmt;
+        Ok(self)
+    }
+
+    fn validate_datetime_fmt(datetime_fmt: &str) -> Result<(), String> {
+        use chrono::format::{Item, StrftimeItems};
+        for item in StrftimeItems::new(datetime_fmt) {
+            if let Item::Error = item {
+                return Err(format!(
+                    "invalid chrono datetime format string: {:?}",
+                    datetime_fmt
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a [`TimeZoneFormatEnvBuilder`] with the same defaults as
+    /// [`TimeZoneFormatEnv::default`].
+    pub fn builder() -> TimeZoneFormatEnvBuilder {
+        TimeZoneFormatEnvBuilder::default()
+    }
+
+    fn with_zone(offset: TimeZone, timestamp_precision: Option<TimestampPrecision>) -> Self {
         let datetime_fmt = if let Some(p) = timestamp_precision {
             match p {
                 TimestampPrecision::Seconds => DATETIME_FMT_SECOND,
                 TimestampPrecision::Millis => DATETIME_FMT_3F,
                 TimestampPrecision::Micros => DATETIME_FMT_6F,
-                TimestampPrecision::Nanos => DATETIME_FMT_6F,
+                TimestampPrecision::Nanos => DATETIME_FMT_9F,
             }
         } else {
             DATETIME_FMT_3F
         };
         Self {
-            datetime_fmt,
+            datetime_fmt: datetime_fmt.to_string(),
             offset,
             module_path: false,
             target: true,
             level: true,
             indent: Some(4),
             suffix: "\n",
+            kv: false,
+            kv_separator: " ",
+            output_format: OutputFormat::Human,
+            styles: TimeZoneStyles::default(),
         }
     }
 }
 
-//type SubtleStyle = StyledValue<&'static str>;
+type SubtleStyle = StyledValue<&'static str>;
 struct StyledValue<T> {
     style: style::Style,
     value: T,
@@ -73,6 +196,69 @@ impl<T: Display> Display for StyledValue<T> {
     }
 }
 
+/// Appends each structured `key=value` pair from a record's `key_values()`
+/// source directly to the formatter, borrowing the buffer so no per-record
+/// allocation is needed.
+struct KeyValueWriter<'a> {
+    buf: &'a mut Formatter,
+    separator: &'static str,
+}
+
+impl<'a, 'kvs> log::kv::VisitSource<'kvs> for KeyValueWriter<'a> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        write!(self.buf, "{}{}={}", self.separator, key, value).map_err(log::kv::Error::boxed)
+    }
+}
+
+/// Writes `s` to `buf` with the minimal escaping required for a JSON string
+/// body (the surrounding quotes are written by the caller).
+fn write_json_str<W: Write>(buf: &mut W, s: &str) -> io::Result<()> {
+    for c in s.chars() {
+        match c {
+            '"' => buf.write_all(b"\\\"")?,
+            '\\' => buf.write_all(b"\\\\")?,
+            '\n' => buf.write_all(b"\\n")?,
+            '\r' => buf.write_all(b"\\r")?,
+            '\t' => buf.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(buf, "\\u{:04x}", c as u32)?,
+            c => write!(buf, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+/// Emits each structured pair as a quoted JSON member into the `fields` object,
+/// rendering values through their `Display` form and escaping as strings.
+struct JsonKeyValueWriter<'a> {
+    buf: &'a mut Formatter,
+    first: bool,
+}
+
+impl<'a, 'kvs> log::kv::VisitSource<'kvs> for JsonKeyValueWriter<'a> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        let res = (|| {
+            if !self.first {
+                self.buf.write_all(b",")?;
+            }
+            self.first = false;
+            self.buf.write_all(b"\"")?;
+            write_json_str(self.buf, key.as_str())?;
+            self.buf.write_all(b"\":\"")?;
+            write_json_str(self.buf, &value.to_string())?;
+            self.buf.write_all(b"\"")
+        })();
+        res.map_err(log::kv::Error::boxed)
+    }
+}
+
 pub struct TimeZoneFormat<'a> {
     env: &'a TimeZoneFormatEnv,
     buf: &'a mut Formatter,
@@ -88,6 +274,10 @@ impl<'a> TimeZoneFormat<'a> {
         }
     }
     pub fn write(mut self, record: &Record) -> io::Result<()> {
+        if self.env.output_format == OutputFormat::Json {
+            return self.write_json(record);
+        }
+
         self.write_timestamp()?;
         self.write_level(record)?;
         self.write_module_path(record)?;
@@ -95,11 +285,15 @@ impl<'a> TimeZoneFormat<'a> {
         self.finish_header()?;
 
         self.write_args(record)?;
+        self.write_key_values(record)?;
         write!(self.buf, "{}", self.env.suffix)
     }
 
-    fn subtle_style(&self, text: &'static str) -> &'static str {
-        text
+    fn subtle_style(&self, text: &'static str) -> SubtleStyle {
+        StyledValue {
+            style: self.env.styles.brackets,
+            value: text,
+        }
     }
 
     fn write_header_value<T>(&mut self, value: T) -> io::Result<()>
@@ -133,10 +327,11 @@ impl<'a> TimeZoneFormat<'a> {
     }
 
     fn write_timestamp(&mut self) -> io::Result<()> {
-        let datetime_str = DateTime::<Utc>::from(SystemTime::now())
-            .with_timezone(&self.env.offset)
-            .format(self.env.datetime_fmt);
-        self.write_header_value(datetime_str)
+        let datetime_str = self.env.offset.format_now(&self.env.datetime_fmt);
+        self.write_header_value(StyledValue {
+            style: self.env.styles.timestamp,
+            value: datetime_str,
+        })
     }
 
     fn write_module_path(&mut self, record: &Record) -> io::Result<()> {
@@ -145,7 +340,10 @@ impl<'a> TimeZoneFormat<'a> {
         }
 
         if let Some(module_path) = record.module_path() {
-            self.write_header_value(module_path)
+            self.write_header_value(StyledValue {
+                style: self.env.styles.module_path,
+                value: module_path,
+            })
         } else {
             Ok(())
         }
@@ -158,7 +356,10 @@ impl<'a> TimeZoneFormat<'a> {
 
         match record.target() {
             "" => Ok(()),
-            target => self.write_header_value(target),
+            target => self.write_header_value(StyledValue {
+                style: self.env.styles.target,
+                value: target,
+            }),
         }
     }
 
@@ -171,6 +372,58 @@ impl<'a> TimeZoneFormat<'a> {
         }
     }
 
+    fn write_json(&mut self, record: &Record) -> io::Result<()> {
+        let timestamp = self.env.offset.now_rfc3339();
+
+        write!(self.buf, "{{\"timestamp\":\"")?;
+        write_json_str(self.buf, &timestamp)?;
+        write!(self.buf, "\",\"level\":\"")?;
+        write_json_str(self.buf, record.level().as_str())?;
+        write!(self.buf, "\",\"target\":\"")?;
+        write_json_str(self.buf, record.target())?;
+        write!(self.buf, "\",\"module_path\":")?;
+        match record.module_path() {
+            Some(module_path) => {
+                write!(self.buf, "\"")?;
+                write_json_str(self.buf, module_path)?;
+                write!(self.buf, "\"")?;
+            }
+            None => write!(self.buf, "null")?,
+        }
+        write!(self.buf, ",\"message\":\"")?;
+        write_json_str(self.buf, &record.args().to_string())?;
+        write!(self.buf, "\",\"fields\":{{")?;
+
+        let mut visitor = JsonKeyValueWriter {
+            buf: self.buf,
+            first: true,
+        };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .map_err(io::Error::other)?;
+
+        write!(self.buf, "}}}}")?;
+        write!(self.buf, "{}", self.env.suffix)
+    }
+
+    fn write_key_values(&mut self, record: &Record) -> io::Result<()> {
+        if !self.env.kv {
+            return Ok(());
+        }
+
+        let kvs = record.key_values();
+        if kvs.count() == 0 {
+            return Ok(());
+        }
+
+        let mut visitor = KeyValueWriter {
+            buf: self.buf,
+            separator: self.env.kv_separator,
+        };
+        kvs.visit(&mut visitor).map_err(io::Error::other)
+    }
+
     fn write_args(&mut self, record: &Record) -> io::Result<()> {
         match self.env.indent {
             // Fast path for no indentation
@@ -223,3 +476,142 @@ impl<'a> TimeZoneFormat<'a> {
         }
     }
 }
+
+/// Chainable builder for [`TimeZoneFormatEnv`].
+///
+/// Prefer this over struct-literal or field-assignment construction: it keeps
+/// a stable public surface so new options can be added without breaking
+/// callers. Zone selection is either a fixed `offset_seconds` or an IANA
+/// `tz_name`; when both are set the named zone wins.
+#[derive(Clone, Debug, Default)]
+pub struct TimeZoneFormatEnvBuilder {
+    offset_seconds: Option<i32>,
+    tz_name: Option<String>,
+    precision: Option<TimestampPrecision>,
+    datetime_fmt: Option<String>,
+    target: Option<bool>,
+    module_path: Option<bool>,
+    level: Option<bool>,
+    indent: Option<Option<usize>>,
+    suffix: Option<&'static str>,
+}
+
+impl TimeZoneFormatEnvBuilder {
+    pub fn offset_seconds(mut self, offset_seconds: i32) -> Self {
+        self.offset_seconds = Some(offset_seconds);
+        self
+    }
+
+    pub fn tz_name(mut self, tz_name: impl Into<String>) -> Self {
+        self.tz_name = Some(tz_name.into());
+        self
+    }
+
+    pub fn precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    pub fn datetime_fmt(mut self, datetime_fmt: impl Into<String>) -> Self {
+        self.datetime_fmt = Some(datetime_fmt.into());
+        self
+    }
+
+    pub fn target(mut self, target: bool) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn module_path(mut self, module_path: bool) -> Self {
+        self.module_path = Some(module_path);
+        self
+    }
+
+    pub fn level(mut self, level: bool) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn indent(mut self, indent: Option<usize>) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+
+    pub fn suffix(mut self, suffix: &'static str) -> Self {
+        self.suffix = Some(suffix);
+        self
+    }
+
+    /// Resolves the zone and applies every configured option.
+    ///
+    /// Returns an error if `tz_name` is not a known IANA zone or if
+    /// `datetime_fmt` is not a valid chrono strftime pattern.
+    pub fn build(self) -> Result<TimeZoneFormatEnv, String> {
+        let mut env = match &self.tz_name {
+            Some(tz_name) => TimeZoneFormatEnv::from_tz_name(tz_name, self.precision)?,
+            None => TimeZoneFormatEnv::new(self.offset_seconds, self.precision),
+        };
+        if let Some(datetime_fmt) = self.datetime_fmt {
+            env = env.with_datetime_fmt(datetime_fmt)?;
+        }
+        if let Some(target) = self.target {
+            env.target = target;
+        }
+        if let Some(module_path) = self.module_path {
+            env.module_path = module_path;
+        }
+        if let Some(level) = self.level {
+            env.level = level;
+        }
+        if let Some(indent) = self.indent {
+            env.indent = indent;
+        }
+        if let Some(suffix) = self.suffix {
+            env.suffix = suffix;
+        }
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_escape(s: &str) -> String {
+        let mut buf = Vec::new();
+        write_json_str(&mut buf, s).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn json_str_escapes_quote_and_backslash() {
+        assert_eq!(json_escape(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+
+    #[test]
+    fn json_str_escapes_control_chars() {
+        assert_eq!(json_escape("line\ntab\t"), "line\\ntab\\t");
+        assert_eq!(json_escape("\u{0001}"), "\\u0001");
+    }
+
+    #[test]
+    fn json_str_passes_through_plain_text() {
+        assert_eq!(json_escape("plain text 123"), "plain text 123");
+    }
+
+    #[test]
+    fn validate_datetime_fmt_accepts_valid_pattern() {
+        assert!(TimeZoneFormatEnv::validate_datetime_fmt("%Y-%m-%d %H:%M:%S%.9f %:z").is_ok());
+    }
+
+    #[test]
+    fn validate_datetime_fmt_rejects_bad_pattern() {
+        assert!(TimeZoneFormatEnv::validate_datetime_fmt("%Q").is_err());
+    }
+
+    #[test]
+    fn nanos_precision_maps_to_9f_pattern() {
+        let env = TimeZoneFormatEnv::new(Some(0), Some(TimestampPrecision::Nanos));
+        assert_eq!(env.datetime_fmt, DATETIME_FMT_9F);
+    }
+}